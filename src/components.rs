@@ -32,3 +32,10 @@ impl Default for Trail {
         }
     }
 }
+
+/// Emitted when `resolve_collisions` merges `absorbed` into `survivor`.
+#[derive(Message)]
+pub struct BodyMergedEvent {
+    pub survivor: Entity,
+    pub absorbed: Entity,
+}
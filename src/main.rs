@@ -1,4 +1,6 @@
 mod components;
+#[cfg(feature = "octree")]
+mod octtree;
 mod quadtree;
 mod resources;
 mod systems;
@@ -7,6 +9,7 @@ use bevy::prelude::*;
 use bevy::window::WindowResolution;
 use bevy_egui::{EguiPlugin, EguiPrimaryContextPass};
 
+use crate::components::BodyMergedEvent;
 use crate::quadtree::QuadTreeResource;
 use crate::resources::ResetSimulation;
 use crate::resources::{DEFAULT_DT, SimulationBounds};
@@ -30,6 +33,7 @@ fn main() {
         .init_resource::<QuadTreeResource>()
         .init_resource::<SimSettings>()
         .init_resource::<ResetSimulation>()
+        .add_message::<BodyMergedEvent>()
         .add_systems(EguiPrimaryContextPass, ui_controls)
         .add_systems(Startup, setup_scene)
         .add_systems(
@@ -49,7 +53,14 @@ fn main() {
         )
         .add_systems(
             FixedUpdate,
-            (reset_and_build_tree, calculate_forces, integrate_motion).chain(),
+            (
+                reset_and_build_tree.run_if(needs_full_tree_rebuild),
+                update_tree_incrementally.run_if(tree_can_refit_incrementally),
+                calculate_forces,
+                resolve_collisions.run_if(collisions_enabled),
+                integrate_motion,
+            )
+                .chain(),
         )
         .insert_resource(Time::<Fixed>::from_seconds(DEFAULT_DT as f64))
         .run();
@@ -0,0 +1,362 @@
+//! Barnes-Hut octree used for approximate n-body force calculation in 3D.
+//!
+//! This mirrors `quadtree.rs` but partitions space into eight octants
+//! instead of four quadrants, so bodies with `Vec3` positions can be
+//! simulated (e.g. galaxy/cluster simulations rather than flat disks).
+//!
+//! Nothing in the running simulation is 3D yet (`Position` is still `Vec2`
+//! throughout `systems`/`components`), so this module has no caller outside
+//! its own tests. It's gated behind the `octree` feature, same as
+//! `quadtree`'s `parallel` feature, so it doesn't trip `dead_code` lints on
+//! the default build; wiring a real 3D body/spawn/integration path is future
+//! work.
+
+use bevy::prelude::*;
+
+use crate::resources::SOFTENING;
+
+/// Axis-aligned cube region.
+#[derive(Clone, Copy, Debug)]
+pub struct Cube {
+    pub center: Vec3,
+    pub size: Vec3,
+}
+
+impl Cube {
+    /// Returns the octant index (0..=7) that contains `point`.
+    pub fn get_octant_index(&self, point: Vec3) -> usize {
+        let right = point.x > self.center.x;
+        let top = point.y > self.center.y;
+        let front = point.z > self.center.z;
+        (right as usize) | ((top as usize) << 1) | ((front as usize) << 2)
+    }
+
+    /// Returns the sub-cube corresponding to `index`.
+    pub fn sub_octant(&self, index: usize) -> Cube {
+        let half_size = self.size / 2.0;
+        let offset = half_size / 2.0;
+        let sign = |bit: usize| if index & bit != 0 { 1.0 } else { -1.0 };
+        let center = self.center
+            + Vec3::new(
+                sign(0b001) * offset.x,
+                sign(0b010) * offset.y,
+                sign(0b100) * offset.z,
+            );
+        Cube {
+            center,
+            size: half_size,
+        }
+    }
+}
+
+/// Logical shape of an octree node.
+#[derive(Clone, Copy)]
+pub enum NodeKind {
+    Empty,
+    Leaf { entity: Entity, position: Vec3 },
+    Internal { children: [Option<usize>; 8] },
+}
+
+/// Barnes-Hut octree node.
+pub struct Node {
+    pub bounds: Cube,
+    pub center_of_mass: Vec3,
+    pub mass: f32,
+    pub kind: NodeKind,
+}
+
+impl Node {
+    /// Creates an empty node covering `bounds`.
+    pub fn empty(bounds: Cube) -> Self {
+        Self {
+            bounds,
+            center_of_mass: Vec3::ZERO,
+            mass: 0.0,
+            kind: NodeKind::Empty,
+        }
+    }
+}
+
+/// Resource storing the octree used for force approximation in 3D.
+#[derive(Resource, Default)]
+pub struct OctTreeResource {
+    pub nodes: Vec<Node>,
+    pub root: Option<usize>,
+}
+
+impl OctTreeResource {
+    /// Clears the tree and inserts a new root covering `bounds`.
+    pub fn reset(&mut self, bounds: Cube) {
+        self.nodes.clear();
+        self.root = Some(self.nodes.len());
+        self.nodes.push(Node::empty(bounds));
+    }
+
+    /// Inserts a body entity with position and mass into the octree.
+    pub fn insert(&mut self, entity: Entity, position: Vec3, mass: f32) {
+        let root_index = match self.root {
+            Some(index) => index,
+            None => return,
+        };
+        self.insert_recursive(root_index, entity, position, mass);
+    }
+
+    fn insert_recursive(&mut self, index: usize, entity: Entity, position: Vec3, mass: f32) {
+        let bounds = self.nodes[index].bounds;
+        match self.nodes[index].kind {
+            NodeKind::Empty => {
+                // Nothing here yet: place a leaf.
+                self.nodes[index].kind = NodeKind::Leaf { entity, position };
+                self.nodes[index].mass = mass;
+                self.nodes[index].center_of_mass = position;
+            }
+            NodeKind::Leaf {
+                entity: existing_entity,
+                position: existing_position,
+            } => {
+                let existing_mass = self.nodes[index].mass;
+                if (existing_position - position).length_squared() < 0.0001 {
+                    // Same location: merge mass and update center of mass.
+                    let total_mass = existing_mass + mass;
+                    self.nodes[index].mass = total_mass;
+                    self.nodes[index].center_of_mass =
+                        (existing_position * existing_mass + position * mass) / total_mass;
+                    return;
+                }
+
+                // Subdivide and reinsert both the existing leaf and the new body.
+                let mut children = [None; 8];
+                self.subdivide(index, &mut children);
+
+                let existing_index = bounds.get_octant_index(existing_position);
+                let new_index = bounds.get_octant_index(position);
+
+                if let Some(child_idx) = children[existing_index] {
+                    self.insert_recursive(
+                        child_idx,
+                        existing_entity,
+                        existing_position,
+                        existing_mass,
+                    );
+                }
+
+                if let Some(child_idx) = children[new_index] {
+                    self.insert_recursive(child_idx, entity, position, mass);
+                }
+
+                let total_mass = existing_mass + mass;
+                let com = (existing_position * existing_mass + position * mass) / total_mass;
+
+                self.nodes[index].kind = NodeKind::Internal { children };
+                self.nodes[index].mass = total_mass;
+                self.nodes[index].center_of_mass = com;
+            }
+            NodeKind::Internal { children } => {
+                // Descend into the child that contains the new position.
+                let child_idx = bounds.get_octant_index(position);
+                if let Some(idx) = children[child_idx] {
+                    self.insert_recursive(idx, entity, position, mass);
+                }
+
+                // Update mass and center of mass on the way back up.
+                let total_mass = self.nodes[index].mass + mass;
+                let com = (self.nodes[index].center_of_mass * self.nodes[index].mass
+                    + position * mass)
+                    / total_mass;
+                self.nodes[index].mass = total_mass;
+                self.nodes[index].center_of_mass = com;
+                self.nodes[index].kind = NodeKind::Internal { children };
+            }
+        }
+    }
+
+    fn subdivide(&mut self, index: usize, children: &mut [Option<usize>; 8]) {
+        for (octant, child) in children.iter_mut().enumerate() {
+            // Create an empty child node for each octant.
+            let child_bounds = self.nodes[index].bounds.sub_octant(octant);
+            let child_index = self.nodes.len();
+            self.nodes.push(Node::empty(child_bounds));
+            *child = Some(child_index);
+        }
+    }
+
+    /// Returns the net gravitational force on `target` using Barnes-Hut approximation.
+    pub fn calculate_force(
+        &self,
+        target: Entity,
+        position: Vec3,
+        config: &crate::resources::SimConfig,
+    ) -> Vec3 {
+        let root = match self.root {
+            Some(idx) => idx,
+            None => return Vec3::ZERO,
+        };
+
+        self.calculate_force_recursive(root, target, position, config)
+    }
+
+    fn calculate_force_recursive(
+        &self,
+        index: usize,
+        target: Entity,
+        position: Vec3,
+        config: &crate::resources::SimConfig,
+    ) -> Vec3 {
+        let node = &self.nodes[index];
+        match node.kind {
+            NodeKind::Empty => Vec3::ZERO,
+            NodeKind::Leaf {
+                entity,
+                position: pos,
+            } => {
+                if entity == target {
+                    return Vec3::ZERO;
+                }
+                // Direct body-body interaction.
+                let delta = pos - position;
+                let dist_sq = delta.length_squared() + SOFTENING * SOFTENING;
+                let dist = dist_sq.sqrt();
+                let force_mag = (config.g * node.mass) / dist_sq;
+                delta / dist * force_mag
+            }
+            NodeKind::Internal { children } => {
+                let delta = node.center_of_mass - position;
+                let dist = delta.length().max(0.0001);
+                // Acceptance uses the cube edge length, same as the quadtree's width check.
+                let width = node.bounds.size.x;
+
+                if width / dist < config.theta {
+                    // Accept approximation: treat node as a single mass at its center of mass.
+                    let dist_sq = dist * dist + SOFTENING * SOFTENING;
+                    let force_mag = (config.g * node.mass) / dist_sq;
+                    delta / dist * force_mag
+                } else {
+                    // Otherwise recurse into children and accumulate force.
+                    let mut total_force = Vec3::ZERO;
+                    for child in children.iter().flatten() {
+                        total_force +=
+                            self.calculate_force_recursive(*child, target, position, config);
+                    }
+                    total_force
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resources::SimConfig;
+
+    fn assert_vec3_close(a: Vec3, b: Vec3, tolerance: f32) {
+        let diff = (a - b).length();
+        assert!(
+            diff <= tolerance,
+            "expected {:?} to be within {} of {:?}, diff {}",
+            a,
+            tolerance,
+            b,
+            diff
+        );
+    }
+
+    #[test]
+    fn octants_and_subdivision_are_consistent() {
+        let cube = Cube {
+            center: Vec3::ZERO,
+            size: Vec3::splat(4.0),
+        };
+
+        assert_eq!(cube.get_octant_index(vec3(-1.0, -1.0, -1.0)), 0);
+        assert_eq!(cube.get_octant_index(vec3(1.0, -1.0, -1.0)), 1);
+        assert_eq!(cube.get_octant_index(vec3(-1.0, 1.0, -1.0)), 2);
+        assert_eq!(cube.get_octant_index(vec3(1.0, 1.0, -1.0)), 3);
+        assert_eq!(cube.get_octant_index(vec3(-1.0, -1.0, 1.0)), 4);
+        assert_eq!(cube.get_octant_index(vec3(1.0, -1.0, 1.0)), 5);
+        assert_eq!(cube.get_octant_index(vec3(-1.0, 1.0, 1.0)), 6);
+        assert_eq!(cube.get_octant_index(vec3(1.0, 1.0, 1.0)), 7);
+
+        for index in 0..8 {
+            let sub = cube.sub_octant(index);
+            assert_vec3_close(sub.size, Vec3::splat(2.0), 0.0001);
+            assert_eq!(cube.get_octant_index(sub.center), index);
+        }
+    }
+
+    #[test]
+    fn insert_combines_overlapping_positions() {
+        let mut octree = OctTreeResource::default();
+        let bounds = Cube {
+            center: Vec3::ZERO,
+            size: Vec3::splat(10.0),
+        };
+        octree.reset(bounds);
+
+        let entity_a = Entity::from_bits(1);
+        let entity_b = Entity::from_bits(2);
+        let position = vec3(1.0, 1.0, 1.0);
+
+        octree.insert(entity_a, position, 2.0);
+        octree.insert(entity_b, position, 3.0);
+
+        let root = octree.root.unwrap();
+        assert_eq!(octree.nodes.len(), 1);
+        let node = &octree.nodes[root];
+        assert!(matches!(node.kind, NodeKind::Leaf { .. }));
+        assert!((node.mass - 5.0).abs() < 0.0001);
+        assert_vec3_close(node.center_of_mass, position, 0.0001);
+    }
+
+    #[test]
+    fn calculate_force_ignores_target_entity() {
+        let mut octree = OctTreeResource::default();
+        let bounds = Cube {
+            center: Vec3::ZERO,
+            size: Vec3::splat(10.0),
+        };
+        octree.reset(bounds);
+
+        let entity = Entity::from_bits(1);
+        octree.insert(entity, Vec3::ZERO, 5.0);
+
+        let config = SimConfig::default();
+        let force = octree.calculate_force(entity, Vec3::ZERO, &config);
+        assert_vec3_close(force, Vec3::ZERO, 0.0001);
+    }
+
+    #[test]
+    fn calculate_force_uses_approximation_for_distant_nodes() {
+        let mut octree = OctTreeResource::default();
+        let bounds = Cube {
+            center: Vec3::ZERO,
+            size: Vec3::splat(10.0),
+        };
+
+        let com = vec3(50.0, 0.0, 0.0);
+        octree.nodes.push(Node {
+            bounds,
+            center_of_mass: com,
+            mass: 8.0,
+            kind: NodeKind::Internal {
+                children: [None; 8],
+            },
+        });
+        octree.root = Some(0);
+
+        let config = SimConfig {
+            theta: 0.5,
+            ..Default::default()
+        };
+        let target = Entity::from_bits(99);
+        let position = Vec3::ZERO;
+
+        let force = octree.calculate_force(target, position, &config);
+        let delta = com - position;
+        let dist = delta.length().max(0.0001);
+        let dist_sq = dist * dist + SOFTENING * SOFTENING;
+        let expected_mag = (config.g * 8.0) / dist_sq;
+        let expected = delta / dist * expected_mag;
+        assert_vec3_close(force, expected, 0.0001);
+    }
+}
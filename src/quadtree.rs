@@ -1,8 +1,10 @@
 //! Barnes-Hut quadtree used for approximate n-body force calculation.
 
 use bevy::prelude::*;
+use smallvec::SmallVec;
+use std::collections::HashMap;
 
-use crate::resources::SOFTENING;
+use crate::resources::{SOFTENING, SimConfig};
 
 /// Axis-aligned square region.
 #[derive(Clone, Copy, Debug)]
@@ -24,6 +26,23 @@ impl Rect {
         }
     }
 
+    /// Returns whether `point` lies within this rectangle's bounds.
+    pub fn contains(&self, point: Vec2) -> bool {
+        let half_size = self.size / 2.0;
+        (point.x - self.center.x).abs() <= half_size.x
+            && (point.y - self.center.y).abs() <= half_size.y
+    }
+
+    /// Returns the squared distance from `point` to the closest point on
+    /// this rectangle (zero if `point` lies inside it).
+    pub fn distance_squared_to(&self, point: Vec2) -> f32 {
+        let half_size = self.size / 2.0;
+        let min = self.center - half_size;
+        let max = self.center + half_size;
+        let clamped = point.clamp(min, max);
+        (point - clamped).length_squared()
+    }
+
     /// Returns the sub-rectangle corresponding to `index`.
     pub fn sub_quadrant(&self, index: usize) -> Rect {
         let quarter_size = self.size / 2.0;
@@ -42,11 +61,17 @@ impl Rect {
     }
 }
 
+/// A body held directly in a leaf bucket: its entity, position, and mass.
+pub type LeafOccupant = (Entity, Vec2, f32);
+
+/// Small inline buffer of bodies sharing a leaf, avoiding one allocation per
+/// leaf for the common case of a handful of occupants.
+pub type LeafBucket = SmallVec<[LeafOccupant; 4]>;
+
 /// Logical shape of a quadtree node.
-#[derive(Clone, Copy)]
 pub enum NodeKind {
     Empty,
-    Leaf { entity: Entity, position: Vec2 },
+    Leaf { occupants: LeafBucket },
     Internal { children: [Option<usize>; 4] },
 }
 
@@ -56,99 +81,194 @@ pub struct Node {
     pub center_of_mass: Vec2,
     pub mass: f32,
     pub kind: NodeKind,
+    /// Index of the parent node, `None` for the root.
+    pub parent: Option<usize>,
+    /// Number of live bodies in this node's subtree.
+    pub count: usize,
+    /// Whether this node's mass/center_of_mass need to be recomputed by `refit`.
+    pub dirty: bool,
+    /// Depth from the root (root is 0), used to bound subdivision.
+    pub depth: u32,
 }
 
 impl Node {
-    /// Creates an empty node covering `bounds`.
-    pub fn empty(bounds: Rect) -> Self {
+    /// Creates an empty node covering `bounds` at `depth` with the given parent.
+    pub fn empty(bounds: Rect, parent: Option<usize>, depth: u32) -> Self {
         Self {
             bounds,
             center_of_mass: Vec2::ZERO,
             mass: 0.0,
             kind: NodeKind::Empty,
+            parent,
+            count: 0,
+            dirty: false,
+            depth,
         }
     }
 }
 
+/// An entity and its squared distance from a `k_nearest` query center,
+/// ordered by distance so it can sit in a bounded max-heap.
+struct DistEntity {
+    dist_sq: f32,
+    entity: Entity,
+}
+
+impl PartialEq for DistEntity {
+    fn eq(&self, other: &Self) -> bool {
+        self.dist_sq == other.dist_sq
+    }
+}
+impl Eq for DistEntity {}
+impl PartialOrd for DistEntity {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+impl Ord for DistEntity {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.dist_sq.total_cmp(&other.dist_sq)
+    }
+}
+
+/// A candidate node and its lower-bound squared distance from a `k_nearest`
+/// query center, used to drive the best-first open-node heap.
+struct DistNode {
+    dist_sq: f32,
+    index: usize,
+}
+
+impl PartialEq for DistNode {
+    fn eq(&self, other: &Self) -> bool {
+        self.dist_sq == other.dist_sq
+    }
+}
+impl Eq for DistNode {}
+impl PartialOrd for DistNode {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+impl Ord for DistNode {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.dist_sq.total_cmp(&other.dist_sq)
+    }
+}
+
 /// Resource storing the quadtree used for force approximation.
 #[derive(Resource, Default)]
 pub struct QuadTreeResource {
     pub nodes: Vec<Node>,
     pub root: Option<usize>,
+    /// Tracks which leaf node currently holds each entity, so `update` can
+    /// refit in place instead of rebuilding the whole tree every frame.
+    entity_leaf: HashMap<Entity, usize>,
 }
 
 impl QuadTreeResource {
     /// Clears the tree and inserts a new root covering `bounds`.
     pub fn reset(&mut self, bounds: Rect) {
         self.nodes.clear();
+        self.entity_leaf.clear();
         self.root = Some(self.nodes.len());
-        self.nodes.push(Node::empty(bounds));
+        self.nodes.push(Node::empty(bounds, None, 0));
     }
 
     /// Inserts a body entity with position and mass into the quadtree.
-    pub fn insert(&mut self, entity: Entity, position: Vec2, mass: f32) {
+    pub fn insert(&mut self, entity: Entity, position: Vec2, mass: f32, config: &SimConfig) {
         let root_index = match self.root {
             Some(index) => index,
             None => return,
         };
-        self.insert_recursive(root_index, entity, position, mass);
+        self.insert_recursive(root_index, entity, position, mass, config);
     }
 
-    fn insert_recursive(&mut self, index: usize, entity: Entity, position: Vec2, mass: f32) {
+    fn insert_recursive(
+        &mut self,
+        index: usize,
+        entity: Entity,
+        position: Vec2,
+        mass: f32,
+        config: &SimConfig,
+    ) {
         let bounds = self.nodes[index].bounds;
-        match self.nodes[index].kind {
+        let depth = self.nodes[index].depth;
+
+        match &self.nodes[index].kind {
             NodeKind::Empty => {
-                // Nothing here yet: place a leaf.
-                self.nodes[index].kind = NodeKind::Leaf { entity, position };
+                let mut occupants = LeafBucket::new();
+                occupants.push((entity, position, mass));
+                self.nodes[index].kind = NodeKind::Leaf { occupants };
                 self.nodes[index].mass = mass;
                 self.nodes[index].center_of_mass = position;
+                self.nodes[index].count = 1;
+                self.entity_leaf.insert(entity, index);
             }
-            NodeKind::Leaf {
-                entity: existing_entity,
-                position: existing_position,
-            } => {
-                let existing_mass = self.nodes[index].mass;
-                if (existing_position - position).length_squared() < 0.0001 {
-                    // Same location: merge mass and update center of mass.
-                    let total_mass = existing_mass + mass;
+            NodeKind::Leaf { occupants } => {
+                let below_capacity = occupants.len() < config.split_threshold;
+
+                if below_capacity || depth >= config.max_depth {
+                    // Room left in the bucket (or we've hit the depth bound):
+                    // accumulate in place instead of subdividing.
+                    if let NodeKind::Leaf { occupants } = &mut self.nodes[index].kind {
+                        occupants.push((entity, position, mass));
+                    }
+                    let total_mass = self.nodes[index].mass + mass;
+                    let com = (self.nodes[index].center_of_mass * self.nodes[index].mass
+                        + position * mass)
+                        / total_mass;
                     self.nodes[index].mass = total_mass;
-                    self.nodes[index].center_of_mass =
-                        (existing_position * existing_mass + position * mass) / total_mass;
+                    self.nodes[index].center_of_mass = com;
+                    self.nodes[index].count += 1;
+                    self.entity_leaf.insert(entity, index);
                     return;
                 }
 
-                // Subdivide and reinsert both the existing leaf and the new body.
+                // Over capacity: subdivide and redistribute the bucket's
+                // occupants plus the new body into the resulting children.
+                let occupants = match std::mem::replace(&mut self.nodes[index].kind, NodeKind::Empty)
+                {
+                    NodeKind::Leaf { occupants } => occupants,
+                    _ => unreachable!(),
+                };
+
                 let mut children = [None, None, None, None];
                 self.subdivide(index, &mut children);
 
-                let existing_index = bounds.get_quadrant_index(existing_position);
-                let new_index = bounds.get_quadrant_index(position);
-
-                if let Some(child_idx) = children[existing_index] {
-                    self.insert_recursive(
-                        child_idx,
-                        existing_entity,
-                        existing_position,
-                        existing_mass,
-                    );
+                for (existing_entity, existing_position, existing_mass) in occupants {
+                    let child_index = bounds.get_quadrant_index(existing_position);
+                    if let Some(child_idx) = children[child_index] {
+                        self.insert_recursive(
+                            child_idx,
+                            existing_entity,
+                            existing_position,
+                            existing_mass,
+                            config,
+                        );
+                    }
                 }
 
-                if let Some(child_idx) = children[new_index] {
-                    self.insert_recursive(child_idx, entity, position, mass);
+                let new_child_index = bounds.get_quadrant_index(position);
+                if let Some(child_idx) = children[new_child_index] {
+                    self.insert_recursive(child_idx, entity, position, mass, config);
                 }
 
-                let total_mass = existing_mass + mass;
-                let com = (existing_position * existing_mass + position * mass) / total_mass;
+                let total_mass = self.nodes[index].mass + mass;
+                let com = (self.nodes[index].center_of_mass * self.nodes[index].mass
+                    + position * mass)
+                    / total_mass;
 
                 self.nodes[index].kind = NodeKind::Internal { children };
                 self.nodes[index].mass = total_mass;
                 self.nodes[index].center_of_mass = com;
+                self.nodes[index].count += 1;
             }
             NodeKind::Internal { children } => {
+                let children = *children;
                 // Descend into the child that contains the new position.
                 let child_idx = bounds.get_quadrant_index(position);
                 if let Some(idx) = children[child_idx] {
-                    self.insert_recursive(idx, entity, position, mass);
+                    self.insert_recursive(idx, entity, position, mass, config);
                 }
 
                 // Update mass and center of mass on the way back up.
@@ -158,28 +278,173 @@ impl QuadTreeResource {
                     / total_mass;
                 self.nodes[index].mass = total_mass;
                 self.nodes[index].center_of_mass = com;
-                self.nodes[index].kind = NodeKind::Internal { children };
+                self.nodes[index].count += 1;
             }
         }
     }
 
     fn subdivide(&mut self, index: usize, children: &mut [Option<usize>; 4]) {
+        let child_depth = self.nodes[index].depth + 1;
         for (quadrant, child) in children.iter_mut().enumerate() {
             // Create an empty child node for each quadrant.
             let child_bounds = self.nodes[index].bounds.sub_quadrant(quadrant);
             let child_index = self.nodes.len();
-            self.nodes.push(Node::empty(child_bounds));
+            self.nodes.push(Node::empty(child_bounds, Some(index), child_depth));
             *child = Some(child_index);
         }
     }
 
+    /// Returns every entity the tree currently tracks in a leaf bucket, so a
+    /// caller doing incremental `update`s can diff this against the live
+    /// query and `remove` whatever dropped out (e.g. despawned bodies).
+    pub fn tracked_entities(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.entity_leaf.keys().copied()
+    }
+
+    /// Updates a tracked entity's position/mass in place when it stays within
+    /// its current leaf's bounds, marking the path to the root dirty. Bodies
+    /// that cross out of their leaf are removed and fully reinserted. Call
+    /// `refit` after a batch of updates to recompute aggregates.
+    pub fn update(&mut self, entity: Entity, position: Vec2, mass: f32, config: &SimConfig) {
+        if let Some(&leaf_index) = self.entity_leaf.get(&entity) {
+            if self.nodes[leaf_index].bounds.contains(position) {
+                if let NodeKind::Leaf { occupants } = &mut self.nodes[leaf_index].kind {
+                    if let Some(slot) = occupants.iter_mut().find(|(e, _, _)| *e == entity) {
+                        *slot = (entity, position, mass);
+                    }
+                    let (total_mass, weighted_pos) = occupants.iter().fold(
+                        (0.0, Vec2::ZERO),
+                        |(total, weighted), &(_, pos, m)| (total + m, weighted + pos * m),
+                    );
+                    self.nodes[leaf_index].mass = total_mass;
+                    self.nodes[leaf_index].center_of_mass = if total_mass > 0.0 {
+                        weighted_pos / total_mass
+                    } else {
+                        Vec2::ZERO
+                    };
+                }
+                self.mark_dirty(leaf_index);
+                return;
+            }
+            self.remove(entity);
+        }
+        self.insert(entity, position, mass, config);
+    }
+
+    /// Removes a tracked entity from its leaf bucket, marking the path to
+    /// the root dirty so `refit` can recompute/collapse it. Safe to call for
+    /// an entity the tree isn't tracking (e.g. already despawned); it's then
+    /// a no-op.
+    pub fn remove(&mut self, entity: Entity) {
+        if let Some(leaf_index) = self.entity_leaf.remove(&entity) {
+            if let NodeKind::Leaf { occupants } = &mut self.nodes[leaf_index].kind {
+                occupants.retain(|(e, _, _)| *e != entity);
+            }
+
+            let (total_mass, weighted_pos, count) = match &self.nodes[leaf_index].kind {
+                NodeKind::Leaf { occupants } => occupants.iter().fold(
+                    (0.0, Vec2::ZERO, 0usize),
+                    |(total, weighted, count), &(_, pos, mass)| {
+                        (total + mass, weighted + pos * mass, count + 1)
+                    },
+                ),
+                _ => (0.0, Vec2::ZERO, 0),
+            };
+
+            if count == 0 {
+                self.nodes[leaf_index].kind = NodeKind::Empty;
+            }
+            self.nodes[leaf_index].mass = total_mass;
+            self.nodes[leaf_index].center_of_mass = if total_mass > 0.0 {
+                weighted_pos / total_mass
+            } else {
+                Vec2::ZERO
+            };
+            self.nodes[leaf_index].count = count;
+            self.mark_dirty(leaf_index);
+        }
+    }
+
+    /// Marks every ancestor of `index` dirty, stopping early once an
+    /// already-dirty ancestor is reached.
+    fn mark_dirty(&mut self, index: usize) {
+        let mut current = self.nodes[index].parent;
+        while let Some(idx) = current {
+            if self.nodes[idx].dirty {
+                break;
+            }
+            self.nodes[idx].dirty = true;
+            current = self.nodes[idx].parent;
+        }
+    }
+
+    /// Bottom-up pass that recomputes `mass`/`center_of_mass` for every dirty
+    /// internal node from its children, and collapses a subtree back into a
+    /// single bucket leaf once its body count drops to `config.split_threshold`
+    /// or below.
+    pub fn refit(&mut self, config: &SimConfig) {
+        if let Some(root) = self.root {
+            self.refit_recursive(root, config);
+        }
+    }
+
+    fn refit_recursive(&mut self, index: usize, config: &SimConfig) {
+        if !self.nodes[index].dirty {
+            return;
+        }
+
+        let children = match &self.nodes[index].kind {
+            NodeKind::Internal { children } => *children,
+            _ => {
+                self.nodes[index].dirty = false;
+                return;
+            }
+        };
+
+        for child in children.iter().flatten() {
+            self.refit_recursive(*child, config);
+        }
+
+        let mut total_mass = 0.0;
+        let mut weighted_pos = Vec2::ZERO;
+        let mut count = 0;
+        for child in children.iter().flatten() {
+            let child_node = &self.nodes[*child];
+            total_mass += child_node.mass;
+            weighted_pos += child_node.center_of_mass * child_node.mass;
+            count += child_node.count;
+        }
+        let com = if total_mass > 0.0 {
+            weighted_pos / total_mass
+        } else {
+            Vec2::ZERO
+        };
+
+        if count == 0 {
+            self.nodes[index].kind = NodeKind::Empty;
+        } else if count <= config.split_threshold {
+            // Few enough bodies remain: collapse the whole subtree back into
+            // a single bucket leaf instead of recomputing as Internal.
+            let mut occupants = LeafBucket::new();
+            for child in children.iter().flatten() {
+                collect_occupants(&self.nodes, *child, &mut occupants);
+            }
+            for &(entity, _, _) in occupants.iter() {
+                self.entity_leaf.insert(entity, index);
+            }
+            self.nodes[index].kind = NodeKind::Leaf { occupants };
+        } else {
+            self.nodes[index].kind = NodeKind::Internal { children };
+        }
+
+        self.nodes[index].mass = total_mass;
+        self.nodes[index].center_of_mass = com;
+        self.nodes[index].count = count;
+        self.nodes[index].dirty = false;
+    }
+
     /// Returns the net gravitational force on `target` using Barnes-Hut approximation.
-    pub fn calculate_force(
-        &self,
-        target: Entity,
-        position: Vec2,
-        config: &crate::resources::SimConfig,
-    ) -> Vec2 {
+    pub fn calculate_force(&self, target: Entity, position: Vec2, config: &SimConfig) -> Vec2 {
         let root = match self.root {
             Some(idx) => idx,
             None => return Vec2::ZERO,
@@ -193,26 +458,28 @@ impl QuadTreeResource {
         index: usize,
         target: Entity,
         position: Vec2,
-        config: &crate::resources::SimConfig,
+        config: &SimConfig,
     ) -> Vec2 {
         let node = &self.nodes[index];
-        match node.kind {
+        match &node.kind {
             NodeKind::Empty => Vec2::ZERO,
-            NodeKind::Leaf {
-                entity,
-                position: pos,
-            } => {
-                if entity == target {
-                    return Vec2::ZERO;
+            NodeKind::Leaf { occupants } => {
+                // Bucket leaf: sum exact body-body interactions, skipping target.
+                let mut total_force = Vec2::ZERO;
+                for &(entity, pos, mass) in occupants.iter() {
+                    if entity == target {
+                        continue;
+                    }
+                    let delta = pos - position;
+                    let dist_sq = delta.length_squared() + SOFTENING * SOFTENING;
+                    let dist = dist_sq.sqrt();
+                    let force_mag = (config.g * mass) / dist_sq;
+                    total_force += delta / dist * force_mag;
                 }
-                // Direct body-body interaction.
-                let delta = pos - position;
-                let dist_sq = delta.length_squared() + SOFTENING * SOFTENING;
-                let dist = dist_sq.sqrt();
-                let force_mag = (config.g * node.mass) / dist_sq;
-                delta / dist * force_mag
+                total_force
             }
             NodeKind::Internal { children } => {
+                let children = *children;
                 let delta = node.center_of_mass - position;
                 let dist = delta.length().max(0.0001);
                 let width = node.bounds.size.x;
@@ -234,6 +501,342 @@ impl QuadTreeResource {
             }
         }
     }
+
+    /// Returns every entity within `radius` of `center`, pruning any subtree
+    /// whose bounds don't intersect the query circle.
+    pub fn query_radius(&self, center: Vec2, radius: f32) -> Vec<Entity> {
+        let mut found = Vec::new();
+        if let Some(root) = self.root {
+            let radius_sq = radius * radius;
+            self.query_radius_recursive(root, center, radius_sq, &mut found);
+        }
+        found
+    }
+
+    fn query_radius_recursive(&self, index: usize, center: Vec2, radius_sq: f32, out: &mut Vec<Entity>) {
+        let node = &self.nodes[index];
+        if node.bounds.distance_squared_to(center) > radius_sq {
+            return;
+        }
+
+        match &node.kind {
+            NodeKind::Empty => {}
+            NodeKind::Leaf { occupants } => {
+                for &(entity, position, _) in occupants.iter() {
+                    if (position - center).length_squared() <= radius_sq {
+                        out.push(entity);
+                    }
+                }
+            }
+            NodeKind::Internal { children } => {
+                for child in children.iter().flatten() {
+                    self.query_radius_recursive(*child, center, radius_sq, out);
+                }
+            }
+        }
+    }
+
+    /// Returns the `k` entities nearest to `center`, sorted by ascending
+    /// distance, via a best-first descent: open nodes are explored in order
+    /// of their bounds' lower-bound distance to `center`, and any subtree
+    /// whose lower bound exceeds the current k-th nearest distance is pruned.
+    pub fn k_nearest(&self, center: Vec2, k: usize) -> Vec<(Entity, f32)> {
+        use std::cmp::Reverse;
+        use std::collections::BinaryHeap;
+
+        if k == 0 {
+            return Vec::new();
+        }
+
+        let root = match self.root {
+            Some(idx) => idx,
+            None => return Vec::new(),
+        };
+
+        // Max-heap of the best k candidates found so far, ordered by distance
+        // so the farthest can be evicted once we exceed k entries.
+        let mut best: BinaryHeap<DistEntity> = BinaryHeap::with_capacity(k + 1);
+        // Min-heap of nodes still to visit, ordered by their lower-bound
+        // distance to `center` so the most promising node is explored first.
+        let mut open: BinaryHeap<Reverse<DistNode>> = BinaryHeap::new();
+        open.push(Reverse(DistNode {
+            dist_sq: self.nodes[root].bounds.distance_squared_to(center),
+            index: root,
+        }));
+
+        while let Some(Reverse(DistNode { dist_sq, index })) = open.pop() {
+            if best.len() >= k {
+                if let Some(worst) = best.peek() {
+                    if dist_sq > worst.dist_sq {
+                        // Every remaining open node is at least this far away.
+                        break;
+                    }
+                }
+            }
+
+            let node = &self.nodes[index];
+            match &node.kind {
+                NodeKind::Empty => {}
+                NodeKind::Leaf { occupants } => {
+                    for &(entity, position, _) in occupants.iter() {
+                        let dist_sq = (position - center).length_squared();
+                        best.push(DistEntity { dist_sq, entity });
+                        if best.len() > k {
+                            best.pop();
+                        }
+                    }
+                }
+                NodeKind::Internal { children } => {
+                    for child in children.iter().flatten() {
+                        let child_dist_sq = self.nodes[*child].bounds.distance_squared_to(center);
+                        if best.len() < k || child_dist_sq <= best.peek().map_or(f32::MAX, |b| b.dist_sq) {
+                            open.push(Reverse(DistNode {
+                                dist_sq: child_dist_sq,
+                                index: *child,
+                            }));
+                        }
+                    }
+                }
+            }
+        }
+
+        best.into_sorted_vec()
+            .into_iter()
+            .map(|d| (d.entity, d.dist_sq.sqrt()))
+            .collect()
+    }
+
+    /// Computes forces for a batch of bodies in parallel with rayon. The
+    /// tree itself is read-only during the traversal, so this is safe once
+    /// the tree has finished building. The sequential `calculate_force` path
+    /// remains the default for small `n`.
+    #[cfg(feature = "parallel")]
+    pub fn calculate_forces_batched(&self, bodies: &[(Entity, Vec2)], config: &SimConfig) -> Vec<Vec2> {
+        use rayon::prelude::*;
+
+        bodies
+            .par_iter()
+            .map(|&(entity, position)| self.calculate_force(entity, position, config))
+            .collect()
+    }
+
+    /// Rebuilds the tree from scratch using a parallel Morton (Z-order) sort
+    /// followed by a bottom-up parallel combine, instead of the sequential
+    /// `insert` path. Leaves are built in contiguous Morton-sorted runs, then
+    /// siblings are combined level by level with each internal node's mass
+    /// and center of mass computed as a mass-weighted reduction of its
+    /// children. Intended for large `n`; the sequential path is the default.
+    #[cfg(feature = "parallel")]
+    pub fn build_parallel(&mut self, bounds: Rect, bodies: &[(Entity, Vec2, f32)], config: &SimConfig) {
+        use rayon::prelude::*;
+
+        self.nodes.clear();
+        self.entity_leaf.clear();
+
+        if bodies.is_empty() {
+            self.root = Some(self.nodes.len());
+            self.nodes.push(Node::empty(bounds, None, 0));
+            return;
+        }
+
+        let depth = parallel::MORTON_DEPTH;
+        let mut sorted: Vec<(Entity, Vec2, f32, u64)> = bodies
+            .par_iter()
+            .map(|&(entity, position, mass)| {
+                (entity, position, mass, parallel::morton_key(bounds, position, depth))
+            })
+            .collect();
+        sorted.par_sort_unstable_by_key(|&(_, _, _, key)| key);
+
+        let mut nodes = parallel::build_subtree(bounds, &sorted, depth, 0, config.split_threshold);
+        // Root is always the first node of the freshly built arena.
+        for (entity, leaf_index) in collect_entity_leaf_indices(&nodes, 0) {
+            self.entity_leaf.insert(entity, leaf_index);
+        }
+        self.root = Some(0);
+        self.nodes.append(&mut nodes);
+    }
+}
+
+/// Walks `occupants`/subtrees rooted at `index` into `out`, used to gather a
+/// collapsing subtree's bodies into a single bucket leaf.
+fn collect_occupants(nodes: &[Node], index: usize, out: &mut LeafBucket) {
+    match &nodes[index].kind {
+        NodeKind::Leaf { occupants } => out.extend(occupants.iter().copied()),
+        NodeKind::Internal { children } => {
+            for child in children.iter().flatten() {
+                collect_occupants(nodes, *child, out);
+            }
+        }
+        NodeKind::Empty => {}
+    }
+}
+
+/// Walks a freshly built arena collecting `(entity, index)` pairs for every
+/// occupant, so the owning tree's `entity_leaf` map can be populated.
+fn collect_entity_leaf_indices(nodes: &[Node], index: usize) -> Vec<(Entity, usize)> {
+    match &nodes[index].kind {
+        NodeKind::Leaf { occupants } => occupants.iter().map(|&(entity, _, _)| (entity, index)).collect(),
+        NodeKind::Internal { children } => children
+            .iter()
+            .flatten()
+            .flat_map(|&child| collect_entity_leaf_indices(nodes, child))
+            .collect(),
+        NodeKind::Empty => Vec::new(),
+    }
+}
+
+/// Helpers backing `QuadTreeResource::build_parallel`.
+#[cfg(feature = "parallel")]
+mod parallel {
+    use super::*;
+    use rayon::prelude::*;
+
+    /// Quantization depth used by `QuadTreeResource::build_parallel`: bodies
+    /// are sorted by a Morton key over a `2^MORTON_DEPTH`-cell-per-axis grid.
+    pub const MORTON_DEPTH: u32 = 16;
+
+    /// Quantizes `position` to a grid of `2^depth` cells per axis within
+    /// `bounds` and interleaves the two grid coordinates into a Morton
+    /// (Z-order) key, so bodies close in space sort close together.
+    pub fn morton_key(bounds: Rect, position: Vec2, depth: u32) -> u64 {
+        let half = bounds.size / 2.0;
+        let min = bounds.center - half;
+        let resolution = 1u32 << depth;
+        let normalized = ((position - min) / bounds.size).clamp(Vec2::ZERO, Vec2::ONE);
+        let x = ((normalized.x * resolution as f32) as u32).min(resolution - 1);
+        let y = ((normalized.y * resolution as f32) as u32).min(resolution - 1);
+        interleave_bits(x) | (interleave_bits(y) << 1)
+    }
+
+    /// Spreads the bits of `v` into the even bit positions of a 64-bit key.
+    fn interleave_bits(v: u32) -> u64 {
+        let mut x = v as u64;
+        x = (x | (x << 16)) & 0x0000_FFFF_0000_FFFF;
+        x = (x | (x << 8)) & 0x00FF_00FF_00FF_00FF;
+        x = (x | (x << 4)) & 0x0F0F_0F0F_0F0F_0F0F;
+        x = (x | (x << 2)) & 0x3333_3333_3333_3333;
+        (x | (x << 1)) & 0x5555_5555_5555_5555
+    }
+
+    /// Returns the quadrant (matching `Rect::get_quadrant_index`'s
+    /// convention) that `key`'s bits select at `level` (0 = most significant
+    /// quantization bit, i.e. the top of the tree).
+    fn quadrant_at_level(key: u64, level: u32) -> usize {
+        let shift = (MORTON_DEPTH - 1 - level) * 2;
+        let x_bit = (key >> shift) & 1;
+        let y_bit = (key >> (shift + 1)) & 1;
+        match (x_bit != 0, y_bit != 0) {
+            (false, true) => 0,
+            (true, true) => 1,
+            (false, false) => 2,
+            (true, false) => 3,
+        }
+    }
+
+    /// Builds a self-contained arena for the subtree covering `bounds`, with
+    /// the subtree's root at index 0. Once a run is at or below
+    /// `split_threshold` (or quantization resolution runs out), it becomes a
+    /// single bucket leaf; otherwise it's partitioned by Morton key at this
+    /// depth and its (up to four) children are built in parallel, then
+    /// combined with a mass-weighted reduction over the children.
+    pub fn build_subtree(
+        bounds: Rect,
+        bodies: &[(Entity, Vec2, f32, u64)],
+        depth_remaining: u32,
+        depth: u32,
+        split_threshold: usize,
+    ) -> Vec<Node> {
+        if bodies.is_empty() {
+            return vec![Node::empty(bounds, None, depth)];
+        }
+
+        if bodies.len() <= split_threshold || depth_remaining == 0 {
+            let mut occupants = LeafBucket::new();
+            let mut total_mass = 0.0;
+            let mut weighted_pos = Vec2::ZERO;
+            for &(entity, position, mass, _) in bodies {
+                occupants.push((entity, position, mass));
+                total_mass += mass;
+                weighted_pos += position * mass;
+            }
+            let com = if total_mass > 0.0 {
+                weighted_pos / total_mass
+            } else {
+                Vec2::ZERO
+            };
+            let mut node = Node::empty(bounds, None, depth);
+            node.kind = NodeKind::Leaf { occupants };
+            node.mass = total_mass;
+            node.center_of_mass = com;
+            node.count = bodies.len();
+            return vec![node];
+        }
+
+        let level = MORTON_DEPTH - depth_remaining;
+        let mut groups: [Vec<(Entity, Vec2, f32, u64)>; 4] = Default::default();
+        for &item in bodies {
+            groups[quadrant_at_level(item.3, level)].push(item);
+        }
+
+        let children: Vec<(usize, Vec<Node>)> = groups
+            .into_par_iter()
+            .enumerate()
+            .filter(|(_, group)| !group.is_empty())
+            .map(|(quadrant, group)| {
+                let child_bounds = bounds.sub_quadrant(quadrant);
+                (
+                    quadrant,
+                    build_subtree(
+                        child_bounds,
+                        &group,
+                        depth_remaining - 1,
+                        depth + 1,
+                        split_threshold,
+                    ),
+                )
+            })
+            .collect();
+
+        let mut nodes = vec![Node::empty(bounds, None, depth)];
+        let mut child_indices: [Option<usize>; 4] = [None, None, None, None];
+        let (total_mass, weighted_pos, total_count) = children
+            .into_iter()
+            .map(|(quadrant, mut subtree)| {
+                let offset = nodes.len();
+                for node in subtree.iter_mut() {
+                    if let Some(parent) = node.parent.as_mut() {
+                        *parent += offset;
+                    }
+                    if let NodeKind::Internal { children } = &mut node.kind {
+                        for child in children.iter_mut().flatten() {
+                            *child += offset;
+                        }
+                    }
+                }
+                subtree[0].parent = Some(0);
+                let (mass, com, count) = (subtree[0].mass, subtree[0].center_of_mass, subtree[0].count);
+                child_indices[quadrant] = Some(offset);
+                nodes.append(&mut subtree);
+                (mass, com * mass, count)
+            })
+            .fold((0.0, Vec2::ZERO, 0usize), |(m, p, c), (cm, cp, cc)| {
+                (m + cm, p + cp, c + cc)
+            });
+
+        let com = if total_mass > 0.0 {
+            weighted_pos / total_mass
+        } else {
+            Vec2::ZERO
+        };
+        nodes[0].kind = NodeKind::Internal {
+            children: child_indices,
+        };
+        nodes[0].mass = total_mass;
+        nodes[0].center_of_mass = com;
+        nodes[0].count = total_count;
+        nodes
+    }
 }
 
 #[cfg(test)]
@@ -279,7 +882,7 @@ mod tests {
     }
 
     #[test]
-    fn insert_combines_overlapping_positions() {
+    fn insert_accumulates_in_bucket_below_split_threshold() {
         let mut quadtree = QuadTreeResource::default();
         let bounds = Rect {
             center: Vec2::ZERO,
@@ -287,19 +890,67 @@ mod tests {
         };
         quadtree.reset(bounds);
 
+        let config = SimConfig::default();
         let entity_a = Entity::from_bits(1);
         let entity_b = Entity::from_bits(2);
         let position = vec2(1.0, 1.0);
 
-        quadtree.insert(entity_a, position, 2.0);
-        quadtree.insert(entity_b, position, 3.0);
+        quadtree.insert(entity_a, position, 2.0, &config);
+        quadtree.insert(entity_b, vec2(1.1, 0.9), 3.0, &config);
 
         let root = quadtree.root.unwrap();
         assert_eq!(quadtree.nodes.len(), 1);
         let node = &quadtree.nodes[root];
-        assert!(matches!(node.kind, NodeKind::Leaf { .. }));
+        assert!(matches!(&node.kind, NodeKind::Leaf { occupants } if occupants.len() == 2));
         assert!((node.mass - 5.0).abs() < 0.0001);
-        assert_vec2_close(node.center_of_mass, position, 0.0001);
+    }
+
+    #[test]
+    fn insert_subdivides_once_bucket_exceeds_split_threshold() {
+        let mut quadtree = QuadTreeResource::default();
+        let bounds = Rect {
+            center: Vec2::ZERO,
+            size: Vec2::splat(10.0),
+        };
+        quadtree.reset(bounds);
+
+        let config = SimConfig {
+            split_threshold: 2,
+            ..Default::default()
+        };
+
+        quadtree.insert(Entity::from_bits(1), vec2(2.0, 2.0), 1.0, &config);
+        quadtree.insert(Entity::from_bits(2), vec2(2.0, 2.1), 1.0, &config);
+        quadtree.insert(Entity::from_bits(3), vec2(-2.0, -2.0), 1.0, &config);
+
+        let root = quadtree.root.unwrap();
+        assert!(matches!(quadtree.nodes[root].kind, NodeKind::Internal { .. }));
+        assert_eq!(quadtree.nodes[root].count, 3);
+    }
+
+    #[test]
+    fn insert_stops_subdividing_at_max_depth() {
+        let mut quadtree = QuadTreeResource::default();
+        let bounds = Rect {
+            center: Vec2::ZERO,
+            size: Vec2::splat(10.0),
+        };
+        quadtree.reset(bounds);
+
+        let config = SimConfig {
+            split_threshold: 1,
+            max_depth: 0,
+            ..Default::default()
+        };
+
+        // Distinct positions, but max_depth of 0 forbids ever subdividing the
+        // root, so both bodies must accumulate in its bucket.
+        quadtree.insert(Entity::from_bits(1), vec2(2.0, 2.0), 1.0, &config);
+        quadtree.insert(Entity::from_bits(2), vec2(-2.0, -2.0), 1.0, &config);
+
+        let root = quadtree.root.unwrap();
+        assert_eq!(quadtree.nodes.len(), 1);
+        assert!(matches!(&quadtree.nodes[root].kind, NodeKind::Leaf { occupants } if occupants.len() == 2));
     }
 
     #[test]
@@ -311,14 +962,46 @@ mod tests {
         };
         quadtree.reset(bounds);
 
+        let config = SimConfig::default();
         let entity = Entity::from_bits(1);
-        quadtree.insert(entity, Vec2::ZERO, 5.0);
+        quadtree.insert(entity, Vec2::ZERO, 5.0, &config);
 
-        let config = SimConfig::default();
         let force = quadtree.calculate_force(entity, Vec2::ZERO, &config);
         assert_vec2_close(force, Vec2::ZERO, 0.0001);
     }
 
+    #[test]
+    fn calculate_force_sums_exact_interactions_for_bucket_leaf() {
+        let mut quadtree = QuadTreeResource::default();
+        let bounds = Rect {
+            center: Vec2::ZERO,
+            size: Vec2::splat(10.0),
+        };
+        quadtree.reset(bounds);
+
+        let config = SimConfig::default();
+        let target = Entity::from_bits(1);
+        let other_a = Entity::from_bits(2);
+        let other_b = Entity::from_bits(3);
+
+        quadtree.insert(target, Vec2::ZERO, 1.0, &config);
+        quadtree.insert(other_a, vec2(1.0, 0.0), 2.0, &config);
+        quadtree.insert(other_b, vec2(0.0, 1.0), 3.0, &config);
+
+        let force = quadtree.calculate_force(target, Vec2::ZERO, &config);
+
+        let compute = |mass: f32, pos: Vec2| {
+            let delta = pos;
+            let dist_sq = delta.length_squared() + SOFTENING * SOFTENING;
+            let dist = dist_sq.sqrt();
+            let force_mag = (config.g * mass) / dist_sq;
+            delta / dist * force_mag
+        };
+        let expected = compute(2.0, vec2(1.0, 0.0)) + compute(3.0, vec2(0.0, 1.0));
+
+        assert_vec2_close(force, expected, 0.0001);
+    }
+
     #[test]
     fn calculate_force_uses_approximation_for_distant_nodes() {
         let mut quadtree = QuadTreeResource::default();
@@ -335,6 +1018,10 @@ mod tests {
             kind: NodeKind::Internal {
                 children: [None, None, None, None],
             },
+            parent: None,
+            count: 2,
+            dirty: false,
+            depth: 0,
         });
         quadtree.root = Some(0);
 
@@ -362,23 +1049,33 @@ mod tests {
             size: Vec2::splat(20.0),
         };
 
+        let mut child_a_occupants = LeafBucket::new();
+        child_a_occupants.push((Entity::from_bits(1), vec2(5.0, 0.0), 2.0));
         let child_a = Node {
             bounds: bounds.sub_quadrant(1),
             center_of_mass: vec2(5.0, 0.0),
             mass: 2.0,
             kind: NodeKind::Leaf {
-                entity: Entity::from_bits(1),
-                position: vec2(5.0, 0.0),
+                occupants: child_a_occupants,
             },
+            parent: Some(0),
+            count: 1,
+            dirty: false,
+            depth: 1,
         };
+        let mut child_b_occupants = LeafBucket::new();
+        child_b_occupants.push((Entity::from_bits(2), vec2(-5.0, 0.0), 3.0));
         let child_b = Node {
             bounds: bounds.sub_quadrant(0),
             center_of_mass: vec2(-5.0, 0.0),
             mass: 3.0,
             kind: NodeKind::Leaf {
-                entity: Entity::from_bits(2),
-                position: vec2(-5.0, 0.0),
+                occupants: child_b_occupants,
             },
+            parent: Some(0),
+            count: 1,
+            dirty: false,
+            depth: 1,
         };
 
         let child_a_mass = child_a.mass;
@@ -393,6 +1090,10 @@ mod tests {
             kind: NodeKind::Internal {
                 children: [Some(2), Some(1), None, None],
             },
+            parent: None,
+            count: 2,
+            dirty: false,
+            depth: 0,
         });
         quadtree.nodes.push(child_a);
         quadtree.nodes.push(child_b);
@@ -418,4 +1119,144 @@ mod tests {
 
         assert_vec2_close(force, expected, 0.0001);
     }
+
+    #[test]
+    fn update_within_leaf_bounds_skips_rebuild() {
+        let mut quadtree = QuadTreeResource::default();
+        let bounds = Rect {
+            center: Vec2::ZERO,
+            size: Vec2::splat(10.0),
+        };
+        quadtree.reset(bounds);
+
+        let config = SimConfig::default();
+        let entity = Entity::from_bits(1);
+        quadtree.insert(entity, vec2(1.0, 1.0), 2.0, &config);
+        let node_count = quadtree.nodes.len();
+
+        quadtree.update(entity, vec2(1.5, 1.2), 2.0, &config);
+        quadtree.refit(&config);
+
+        assert_eq!(quadtree.nodes.len(), node_count);
+        let root = &quadtree.nodes[quadtree.root.unwrap()];
+        assert_vec2_close(root.center_of_mass, vec2(1.5, 1.2), 0.0001);
+    }
+
+    #[test]
+    fn query_radius_prunes_nodes_outside_the_circle() {
+        let mut quadtree = QuadTreeResource::default();
+        let bounds = Rect {
+            center: Vec2::ZERO,
+            size: Vec2::splat(20.0),
+        };
+        quadtree.reset(bounds);
+
+        let config = SimConfig {
+            split_threshold: 1,
+            ..Default::default()
+        };
+        let near = Entity::from_bits(1);
+        let far = Entity::from_bits(2);
+        quadtree.insert(near, vec2(1.0, 0.0), 1.0, &config);
+        quadtree.insert(far, vec2(9.0, 9.0), 1.0, &config);
+
+        let found = quadtree.query_radius(Vec2::ZERO, 2.0);
+        assert_eq!(found, vec![near]);
+    }
+
+    #[test]
+    fn k_nearest_returns_closest_entities_sorted_by_distance() {
+        let mut quadtree = QuadTreeResource::default();
+        let bounds = Rect {
+            center: Vec2::ZERO,
+            size: Vec2::splat(20.0),
+        };
+        quadtree.reset(bounds);
+
+        let config = SimConfig {
+            split_threshold: 1,
+            ..Default::default()
+        };
+        let closest = Entity::from_bits(1);
+        let middle = Entity::from_bits(2);
+        let farthest = Entity::from_bits(3);
+        quadtree.insert(farthest, vec2(8.0, 0.0), 1.0, &config);
+        quadtree.insert(closest, vec2(1.0, 0.0), 1.0, &config);
+        quadtree.insert(middle, vec2(4.0, 0.0), 1.0, &config);
+
+        let nearest = quadtree.k_nearest(Vec2::ZERO, 2);
+        assert_eq!(nearest.len(), 2);
+        assert_eq!(nearest[0].0, closest);
+        assert_eq!(nearest[1].0, middle);
+        assert!((nearest[0].1 - 1.0).abs() < 0.0001);
+        assert!((nearest[1].1 - 4.0).abs() < 0.0001);
+    }
+
+    #[test]
+    #[cfg(feature = "parallel")]
+    fn build_parallel_places_bodies_in_containing_bounds() {
+        let mut quadtree = QuadTreeResource::default();
+        let bounds = Rect {
+            center: Vec2::ZERO,
+            size: Vec2::splat(10.0),
+        };
+
+        let config = SimConfig {
+            split_threshold: 1,
+            ..Default::default()
+        };
+        let bodies = [
+            (Entity::from_bits(1), vec2(3.0, 3.0), 1.0),
+            (Entity::from_bits(2), vec2(-3.0, 3.0), 1.0),
+            (Entity::from_bits(3), vec2(-3.0, -3.0), 1.0),
+            (Entity::from_bits(4), vec2(3.0, -3.0), 1.0),
+        ];
+
+        quadtree.build_parallel(bounds, &bodies, &config);
+
+        for &(entity, position, _) in &bodies {
+            let leaf_index = *quadtree
+                .entity_leaf
+                .get(&entity)
+                .expect("entity should be tracked in a leaf after build_parallel");
+            let leaf = &quadtree.nodes[leaf_index];
+            assert!(
+                leaf.bounds.contains(position),
+                "entity at {:?} landed in a leaf with bounds {:?}",
+                position,
+                leaf.bounds
+            );
+        }
+    }
+
+    #[test]
+    fn refit_collapses_subtree_after_removal() {
+        let mut quadtree = QuadTreeResource::default();
+        let bounds = Rect {
+            center: Vec2::ZERO,
+            size: Vec2::splat(10.0),
+        };
+        quadtree.reset(bounds);
+
+        let config = SimConfig {
+            split_threshold: 1,
+            ..Default::default()
+        };
+        let entity_a = Entity::from_bits(1);
+        let entity_b = Entity::from_bits(2);
+        quadtree.insert(entity_a, vec2(2.0, 2.0), 1.0, &config);
+        quadtree.insert(entity_b, vec2(-2.0, -2.0), 1.0, &config);
+
+        let root = quadtree.root.unwrap();
+        assert!(matches!(quadtree.nodes[root].kind, NodeKind::Internal { .. }));
+
+        // Remove entity_b; the subtree now holds a single body, at or below
+        // split_threshold, and should collapse back into a bucket leaf.
+        quadtree.remove(entity_b);
+        quadtree.refit(&config);
+
+        let root_node = &quadtree.nodes[root];
+        assert!(matches!(&root_node.kind, NodeKind::Leaf { occupants } if occupants.len() == 1));
+        assert!((root_node.mass - 1.0).abs() < 0.0001);
+    }
 }
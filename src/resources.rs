@@ -6,6 +6,15 @@ pub struct SimConfig {
     pub g: f32,
     pub theta: f32,
     pub dt: f32,
+    /// Number of bodies a leaf bucket holds before it subdivides.
+    pub split_threshold: usize,
+    /// Depth below which a leaf keeps accumulating bodies instead of
+    /// subdividing further, bounding tree height for tight clusters.
+    pub max_depth: u32,
+    /// Whether overlapping bodies are merged by `resolve_collisions`.
+    pub enable_collisions: bool,
+    /// Bodies within this distance of each other are merged.
+    pub collision_radius: f32,
 }
 
 // --- Simulation Defaults ---
@@ -25,6 +34,12 @@ pub const TRAIL_LENGTH: usize = 20;
 pub const CULL_DISTANCE: f32 = 1500.0;
 /// Smallest quadtree node size that will be drawn as a gizmo.
 pub const MIN_GIZMO_NODE_SIZE: f32 = 2.0;
+/// Default leaf bucket capacity before a quadtree node subdivides.
+pub const DEFAULT_SPLIT_THRESHOLD: usize = 4;
+/// Default max quadtree depth before a leaf just accumulates bodies.
+pub const DEFAULT_MAX_DEPTH: u32 = 24;
+/// Default distance within which overlapping bodies are merged.
+pub const DEFAULT_COLLISION_RADIUS: f32 = 2.0;
 
 impl Default for SimConfig {
     fn default() -> Self {
@@ -32,6 +47,10 @@ impl Default for SimConfig {
             g: DEFAULT_G,
             theta: DEFAULT_THETA,
             dt: DEFAULT_DT,
+            split_threshold: DEFAULT_SPLIT_THRESHOLD,
+            max_depth: DEFAULT_MAX_DEPTH,
+            enable_collisions: false,
+            collision_radius: DEFAULT_COLLISION_RADIUS,
         }
     }
 }
@@ -61,6 +80,9 @@ pub struct SimSettings {
     pub enable_culling: bool,
     pub follow_com: bool,
     pub show_gizmos: bool,
+    /// When set, the tree is refit incrementally each frame instead of being
+    /// fully rebuilt from scratch.
+    pub dynamic_tree: bool,
 }
 
 impl Default for SimSettings {
@@ -71,6 +93,7 @@ impl Default for SimSettings {
             enable_culling: false,
             follow_com: false,
             show_gizmos: false,
+            dynamic_tree: false,
         }
     }
 }
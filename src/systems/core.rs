@@ -1,9 +1,10 @@
 use bevy::ecs::system::SystemParam;
 use bevy::input::mouse::MouseWheel;
-use bevy::prelude::MessageReader;
+use bevy::prelude::{MessageReader, MessageWriter};
 use bevy::prelude::*;
 use bevy_egui::input::EguiWantsInput;
 use rand::{Rng, SeedableRng, rngs::StdRng};
+use std::collections::{HashMap, HashSet};
 
 use crate::components::*;
 use crate::quadtree::{NodeKind, QuadTreeResource, Rect};
@@ -84,6 +85,7 @@ pub fn reset_and_build_tree(
     mut quadtree: ResMut<QuadTreeResource>,
     mut bounds: ResMut<SimulationBounds>,
     query: Query<(Entity, &Position, &Mass)>,
+    config: Res<SimConfig>,
 ) {
     let mut min = Vec2::splat(f32::INFINITY);
     let mut max = Vec2::splat(f32::NEG_INFINITY);
@@ -112,8 +114,49 @@ pub fn reset_and_build_tree(
     quadtree.reset(root_bounds);
 
     for (entity, pos, mass) in query.iter() {
-        quadtree.insert(entity, **pos, **mass);
+        quadtree.insert(entity, **pos, **mass, &config);
+    }
+}
+
+/// Run condition: true when the tree needs a full rebuild, either because
+/// dynamic refitting is disabled or because no tree has been built yet.
+pub fn needs_full_tree_rebuild(settings: Res<SimSettings>, quadtree: Res<QuadTreeResource>) -> bool {
+    !settings.dynamic_tree || quadtree.root.is_none()
+}
+
+/// Run condition: true when an existing tree can be refit incrementally
+/// instead of rebuilt from scratch.
+pub fn tree_can_refit_incrementally(
+    settings: Res<SimSettings>,
+    quadtree: Res<QuadTreeResource>,
+) -> bool {
+    settings.dynamic_tree && quadtree.root.is_some()
+}
+
+/// Refits the existing quadtree in place: bodies that stayed within their
+/// current leaf are updated without moving, bodies that crossed out are
+/// removed and reinserted, bodies the tree tracks but the query no longer
+/// sees (despawned by culling or merging) are removed outright, and a single
+/// bottom-up pass recomputes aggregates. Cheaper than `reset_and_build_tree`
+/// when most bodies barely move per frame.
+pub fn update_tree_incrementally(
+    mut quadtree: ResMut<QuadTreeResource>,
+    query: Query<(Entity, &Position, &Mass)>,
+    config: Res<SimConfig>,
+) {
+    let live: HashSet<Entity> = query.iter().map(|(entity, _, _)| entity).collect();
+    let despawned: Vec<Entity> = quadtree
+        .tracked_entities()
+        .filter(|entity| !live.contains(entity))
+        .collect();
+    for entity in despawned {
+        quadtree.remove(entity);
+    }
+
+    for (entity, pos, mass) in query.iter() {
+        quadtree.update(entity, **pos, **mass, &config);
     }
+    quadtree.refit(&config);
 }
 
 /// Uses the quadtree to approximate gravitational forces and updates accelerations.
@@ -130,6 +173,73 @@ pub fn calculate_forces(
         });
 }
 
+/// Run condition: true when body merging is enabled in `SimConfig`.
+pub fn collisions_enabled(config: Res<SimConfig>) -> bool {
+    config.enable_collisions
+}
+
+/// Merges bodies within `SimConfig::collision_radius` of each other,
+/// conserving total mass and momentum and despawning the absorbed entity.
+/// Candidate pairs come from the quadtree's `query_radius`, which is nearly
+/// free here since leaf buckets already group spatially-close bodies.
+pub fn resolve_collisions(
+    mut commands: Commands,
+    quadtree: Res<QuadTreeResource>,
+    config: Res<SimConfig>,
+    mut query: Query<(Entity, &mut Position, &mut Velocity, &mut Mass)>,
+    mut merge_events: MessageWriter<BodyMergedEvent>,
+) {
+    let snapshot: Vec<(Entity, Vec2)> = query.iter().map(|(entity, pos, _, _)| (entity, **pos)).collect();
+    let mut merged: HashMap<Entity, (Vec2, Vec2, f32)> = query
+        .iter()
+        .map(|(entity, pos, vel, mass)| (entity, (**pos, **vel, **mass)))
+        .collect();
+    let mut absorbed: HashSet<Entity> = HashSet::new();
+
+    for &(entity, position) in &snapshot {
+        if absorbed.contains(&entity) {
+            continue;
+        }
+
+        for other in quadtree.query_radius(position, config.collision_radius) {
+            if other == entity || absorbed.contains(&other) {
+                continue;
+            }
+            // The tree can still hand back an entity the live query no
+            // longer has (e.g. despawned by an earlier merge this frame, or
+            // stale tracking in the dynamic-refit tree); skip it rather than
+            // indexing `merged` and panicking.
+            let Some(&(pos_b, vel_b, mass_b)) = merged.get(&other) else {
+                continue;
+            };
+
+            let (pos_a, vel_a, mass_a) = merged[&entity];
+            let total_mass = mass_a + mass_b;
+            let merged_position = (pos_a * mass_a + pos_b * mass_b) / total_mass;
+            let merged_velocity = (vel_a * mass_a + vel_b * mass_b) / total_mass;
+
+            merged.insert(entity, (merged_position, merged_velocity, total_mass));
+            absorbed.insert(other);
+            merge_events.write(BodyMergedEvent {
+                survivor: entity,
+                absorbed: other,
+            });
+        }
+    }
+
+    for (entity, (position, velocity, mass)) in merged {
+        if absorbed.contains(&entity) {
+            commands.entity(entity).despawn();
+            continue;
+        }
+        if let Ok((_, mut pos, mut vel, mut body_mass)) = query.get_mut(entity) {
+            pos.0 = position;
+            vel.0 = velocity;
+            body_mass.0 = mass;
+        }
+    }
+}
+
 /// Integrates positions and velocities with a fixed timestep scaled by user settings.
 pub fn integrate_motion(
     mut query: Query<(
@@ -365,15 +475,22 @@ mod tests {
             world.spawn((Position(pos), Mass(mass)));
         }
 
+        // A split threshold of 1 makes the second body subdivide the root.
+        world.insert_resource(SimConfig {
+            split_threshold: 1,
+            ..Default::default()
+        });
+
         let mut system_state: SystemState<(
             ResMut<QuadTreeResource>,
             ResMut<SimulationBounds>,
             Query<(Entity, &Position, &Mass)>,
+            Res<SimConfig>,
         )> = SystemState::new(&mut world);
 
         {
-            let (quadtree, bounds, query) = system_state.get_mut(&mut world);
-            reset_and_build_tree(quadtree, bounds, query);
+            let (quadtree, bounds, query, config) = system_state.get_mut(&mut world);
+            reset_and_build_tree(quadtree, bounds, query, config);
         }
         system_state.apply(&mut world);
 
@@ -408,11 +525,12 @@ mod tests {
             .spawn((Position(vec2(3.0, 0.0)), Mass(2.0), Acceleration(Vec2::ZERO)))
             .id();
 
-        quadtree.insert(entity_a, Vec2::ZERO, 1.0);
-        quadtree.insert(entity_b, vec2(3.0, 0.0), 2.0);
+        let config = SimConfig::default();
+        quadtree.insert(entity_a, Vec2::ZERO, 1.0, &config);
+        quadtree.insert(entity_b, vec2(3.0, 0.0), 2.0, &config);
 
         world.insert_resource(quadtree);
-        world.insert_resource(SimConfig::default());
+        world.insert_resource(config);
 
         let mut system_state: SystemState<(
             Query<(Entity, &Position, &Mass, &mut Acceleration)>,
@@ -454,6 +572,68 @@ mod tests {
         assert!((acc_a.x - expected_a_x).abs() < 0.0001);
         assert!((acc_b.x - expected_b_x).abs() < 0.0001);
     }
+
+    #[test]
+    fn resolve_collisions_merges_overlapping_bodies_conserving_momentum() {
+        let mut world = World::new();
+        let config = SimConfig {
+            collision_radius: 1.0,
+            ..Default::default()
+        };
+
+        let mut quadtree = QuadTreeResource::default();
+        let bounds = Rect {
+            center: Vec2::ZERO,
+            size: Vec2::splat(10.0),
+        };
+        quadtree.reset(bounds);
+
+        let entity_a = world
+            .spawn((
+                Position(Vec2::ZERO),
+                Velocity(vec2(1.0, 0.0)),
+                Mass(1.0),
+            ))
+            .id();
+        let entity_b = world
+            .spawn((
+                Position(vec2(0.5, 0.0)),
+                Velocity(vec2(-1.0, 0.0)),
+                Mass(1.0),
+            ))
+            .id();
+
+        quadtree.insert(entity_a, Vec2::ZERO, 1.0, &config);
+        quadtree.insert(entity_b, vec2(0.5, 0.0), 1.0, &config);
+
+        world.insert_resource(quadtree);
+        world.insert_resource(config);
+        world.init_resource::<Messages<BodyMergedEvent>>();
+
+        let mut system_state: SystemState<(
+            Commands,
+            Res<QuadTreeResource>,
+            Res<SimConfig>,
+            Query<(Entity, &mut Position, &mut Velocity, &mut Mass)>,
+            MessageWriter<BodyMergedEvent>,
+        )> = SystemState::new(&mut world);
+
+        {
+            let (commands, quadtree, config, query, merge_events) =
+                system_state.get_mut(&mut world);
+            resolve_collisions(commands, quadtree, config, query, merge_events);
+        }
+        system_state.apply(&mut world);
+
+        let mut survivors = world.query::<(Entity, &Position, &Velocity, &Mass)>();
+        let remaining: Vec<_> = survivors.iter(&world).collect();
+        assert_eq!(remaining.len(), 1);
+
+        let (_, pos, vel, mass) = remaining[0];
+        assert_vec2_close(pos.0, vec2(0.25, 0.0), 0.0001);
+        assert_vec2_close(vel.0, Vec2::ZERO, 0.0001);
+        assert!((mass.0 - 2.0).abs() < 0.0001);
+    }
 }
 
 /// Responds to a pending reset: clears entities, resets resources, and respawns bodies.
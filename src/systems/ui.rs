@@ -32,6 +32,11 @@ pub fn ui_controls(
                     egui::Slider::new(&mut sim_config.theta, 0.1..=1.0)
                         .text("Theta (Approximation)"),
                 );
+                ui.add(
+                    egui::Slider::new(&mut sim_config.split_threshold, 1..=16)
+                        .text("Leaf Split Threshold"),
+                );
+                ui.add(egui::Slider::new(&mut sim_config.max_depth, 1..=32).text("Max Tree Depth"));
 
                 ui.separator();
                 ui.heading("Gizmos & Behaviors");
@@ -39,6 +44,12 @@ pub fn ui_controls(
                 ui.checkbox(&mut settings.enable_culling, "Enable Culling (>1500 units)");
                 ui.checkbox(&mut settings.follow_com, "Follow Center of Mass");
                 ui.checkbox(&mut settings.show_gizmos, "Show QuadTree Grid");
+                ui.checkbox(&mut settings.dynamic_tree, "Dynamic Tree Refit");
+                ui.checkbox(&mut sim_config.enable_collisions, "Merge Colliding Bodies");
+                ui.add(
+                    egui::Slider::new(&mut sim_config.collision_radius, 0.1..=20.0)
+                        .text("Collision Radius"),
+                );
 
                 ui.separator();
                 ui.heading("Controls");